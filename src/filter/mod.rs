@@ -0,0 +1,408 @@
+use std::io::{Cursor, Read, Write};
+
+use crate::{Script, VarInt};
+use anyhow::*;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::throw_str;
+
+/**
+ * Golomb-Rice parameters from BIP158's "basic" filter type.
+ * https://github.com/bitcoin/bips/blob/master/bip-0158.mediawiki
+ */
+const P: u8 = 19;
+const M: u64 = 784931;
+
+fn sip_round(v0: &mut u64, v1: &mut u64, v2: &mut u64, v3: &mut u64) {
+  *v0 = v0.wrapping_add(*v1);
+  *v1 = v1.rotate_left(13);
+  *v1 ^= *v0;
+  *v0 = v0.rotate_left(32);
+
+  *v2 = v2.wrapping_add(*v3);
+  *v3 = v3.rotate_left(16);
+  *v3 ^= *v2;
+
+  *v0 = v0.wrapping_add(*v3);
+  *v3 = v3.rotate_left(21);
+  *v3 ^= *v0;
+
+  *v2 = v2.wrapping_add(*v1);
+  *v1 = v1.rotate_left(17);
+  *v1 ^= *v2;
+  *v2 = v2.rotate_left(32);
+}
+
+/**
+ * SipHash-2-4 keyed hash, as used by BIP158 to map arbitrary items onto 64-bit values.
+ */
+fn sip_hash_2_4(key: &[u8; 16], data: &[u8]) -> u64 {
+  let k0 = u64::from_le_bytes(key[0..8].try_into().unwrap());
+  let k1 = u64::from_le_bytes(key[8..16].try_into().unwrap());
+
+  let mut v0 = 0x736f6d6570736575u64 ^ k0;
+  let mut v1 = 0x646f72616e646f6du64 ^ k1;
+  let mut v2 = 0x6c7967656e657261u64 ^ k0;
+  let mut v3 = 0x7465646279746573u64 ^ k1;
+
+  let tail_byte = (data.len() as u64) << 56;
+
+  let mut chunks = data.chunks_exact(8);
+  for chunk in &mut chunks {
+    let m = u64::from_le_bytes(chunk.try_into().unwrap());
+
+    v3 ^= m;
+    sip_round(&mut v0, &mut v1, &mut v2, &mut v3);
+    sip_round(&mut v0, &mut v1, &mut v2, &mut v3);
+    v0 ^= m;
+  }
+
+  let mut last_block = [0u8; 8];
+  last_block[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+  let m = u64::from_le_bytes(last_block) | tail_byte;
+
+  v3 ^= m;
+  sip_round(&mut v0, &mut v1, &mut v2, &mut v3);
+  sip_round(&mut v0, &mut v1, &mut v2, &mut v3);
+  v0 ^= m;
+
+  v2 ^= 0xff;
+  sip_round(&mut v0, &mut v1, &mut v2, &mut v3);
+  sip_round(&mut v0, &mut v1, &mut v2, &mut v3);
+  sip_round(&mut v0, &mut v1, &mut v2, &mut v3);
+  sip_round(&mut v0, &mut v1, &mut v2, &mut v3);
+
+  v0 ^ v1 ^ v2 ^ v3
+}
+
+/**
+ * Maps `item` into the range `[0, modulus)` via SipHash-2-4 and the 128-bit
+ * multiply-and-shift reduction `(hash * modulus) >> 64`.
+ */
+fn hash_to_range(key: &[u8; 16], item: &[u8], modulus: u64) -> u64 {
+  let hash = sip_hash_2_4(key, item);
+  (((hash as u128) * (modulus as u128)) >> 64) as u64
+}
+
+#[derive(Default)]
+struct BitWriter {
+  bytes: Vec<u8>,
+  bit_pos: u8,
+}
+
+impl BitWriter {
+  fn write_bit(&mut self, bit: bool) {
+    if self.bit_pos == 0 {
+      self.bytes.push(0);
+    }
+
+    if bit {
+      *self.bytes.last_mut().unwrap() |= 1 << (7 - self.bit_pos);
+    }
+
+    self.bit_pos = (self.bit_pos + 1) % 8;
+  }
+
+  fn write_bits(&mut self, value: u64, n_bits: u8) {
+    for i in (0..n_bits).rev() {
+      self.write_bit((value >> i) & 1 == 1);
+    }
+  }
+
+  /**
+   * Writes `delta` Golomb-Rice encoded with parameter P: the quotient `delta >> P` in
+   * unary (that many 1 bits terminated by a 0 bit), then the remainder in P fixed bits.
+   */
+  fn write_golomb(&mut self, delta: u64) {
+    let quotient = delta >> P;
+    let remainder = delta & ((1u64 << P) - 1);
+
+    for _ in 0..quotient {
+      self.write_bit(true);
+    }
+    self.write_bit(false);
+
+    self.write_bits(remainder, P);
+  }
+}
+
+struct BitReader<'a> {
+  bytes: &'a [u8],
+  bit_pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+  fn new(bytes: &'a [u8]) -> Self {
+    BitReader { bytes, bit_pos: 0 }
+  }
+
+  fn read_bit(&mut self) -> Option<bool> {
+    let byte_index = self.bit_pos / 8;
+    if byte_index >= self.bytes.len() {
+      return None;
+    }
+
+    let bit_index = 7 - (self.bit_pos % 8);
+    self.bit_pos += 1;
+
+    Some((self.bytes[byte_index] >> bit_index) & 1 == 1)
+  }
+
+  fn read_bits(&mut self, n_bits: u8) -> Option<u64> {
+    let mut value = 0u64;
+    for _ in 0..n_bits {
+      value = (value << 1) | (self.read_bit()? as u64);
+    }
+    Some(value)
+  }
+
+  fn read_golomb(&mut self) -> Option<u64> {
+    let mut quotient = 0u64;
+    while self.read_bit()? {
+      quotient += 1;
+    }
+
+    let remainder = self.read_bits(P)?;
+    Some((quotient << P) | remainder)
+  }
+}
+
+/**
+ * A BIP158-style Golomb-Rice coded set, letting a light client probabilistically test
+ * whether a block's scripts intersect a wallet's watched set without downloading it.
+ */
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct GolombFilter {
+  key: [u8; 16],
+  n: u64,
+  filter_data: Vec<u8>,
+}
+
+impl GolombFilter {
+  /**
+   * Builds a filter over the scripts found in a set of transactions (or any other
+   * source of locking/unlocking scripts). `key` is the first 16 bytes of the block hash.
+   */
+  pub fn from_scripts_impl(key: &[u8], scripts: &[Script]) -> Result<GolombFilter> {
+    let key: [u8; 16] = key.try_into().map_err(|_| anyhow!("GolombFilter key must be exactly 16 bytes"))?;
+
+    let mut items: Vec<Vec<u8>> = scripts.iter().map(|script| script.to_bytes()).collect();
+    items.sort_unstable();
+    items.dedup();
+
+    let n = items.len() as u64;
+
+    if n == 0 {
+      return Ok(GolombFilter { key, n: 0, filter_data: vec![] });
+    }
+
+    let modulus = n * M;
+    let mut hashed: Vec<u64> = items.iter().map(|item| hash_to_range(&key, item, modulus)).collect();
+    hashed.sort_unstable();
+
+    let mut writer = BitWriter::default();
+    let mut last_value = 0u64;
+
+    for value in hashed {
+      writer.write_golomb(value - last_value);
+      last_value = value;
+    }
+
+    Ok(GolombFilter {
+      key,
+      n,
+      filter_data: writer.bytes,
+    })
+  }
+
+  fn match_targets(&self, targets: &[u64]) -> bool {
+    if self.n == 0 || targets.is_empty() {
+      return false;
+    }
+
+    let mut targets = targets.to_vec();
+    targets.sort_unstable();
+    targets.dedup();
+
+    let mut reader = BitReader::new(&self.filter_data);
+    let mut value = 0u64;
+    let mut target_index = 0usize;
+
+    for _ in 0..self.n {
+      let delta = match reader.read_golomb() {
+        Some(v) => v,
+        None => return false,
+      };
+      value += delta;
+
+      while target_index < targets.len() && targets[target_index] < value {
+        target_index += 1;
+      }
+
+      if target_index < targets.len() && targets[target_index] == value {
+        return true;
+      }
+    }
+
+    false
+  }
+
+  /**
+   * Decodes the delta stream once, returning whether any of `scripts` are in the filter.
+   */
+  pub fn match_any_impl(&self, scripts: &[Script]) -> bool {
+    let modulus = self.n * M;
+    let targets: Vec<u64> = scripts.iter().map(|script| hash_to_range(&self.key, &script.to_bytes(), modulus)).collect();
+
+    self.match_targets(&targets)
+  }
+
+  pub fn match_single_impl(&self, script: &Script) -> bool {
+    self.match_any_impl(std::slice::from_ref(script))
+  }
+
+  pub fn to_bytes_impl(&self) -> Result<Vec<u8>> {
+    let mut buffer: Vec<u8> = vec![];
+    buffer.write_varint(self.n)?;
+    buffer.write(&self.filter_data)?;
+
+    Ok(buffer)
+  }
+
+  /**
+   * `key` must be the same 16-byte block hash prefix the filter was built with - BIP158
+   * filter bytes on their own don't carry the key.
+   */
+  pub fn from_bytes_impl(key: &[u8], bytes: &[u8]) -> Result<GolombFilter> {
+    let key: [u8; 16] = key.try_into().map_err(|_| anyhow!("GolombFilter key must be exactly 16 bytes"))?;
+
+    let mut cursor = Cursor::new(bytes);
+    let n = cursor.read_varint()?;
+
+    let mut filter_data = vec![];
+    cursor.read_to_end(&mut filter_data)?;
+
+    Ok(GolombFilter { key, n, filter_data })
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl GolombFilter {
+  pub fn from_scripts(key: &[u8], scripts: &[Script]) -> Result<GolombFilter> {
+    GolombFilter::from_scripts_impl(key, scripts)
+  }
+
+  pub fn match_any(&self, scripts: &[Script]) -> bool {
+    self.match_any_impl(scripts)
+  }
+
+  pub fn match_single(&self, script: &Script) -> bool {
+    self.match_single_impl(script)
+  }
+
+  pub fn to_bytes(&self) -> Result<Vec<u8>> {
+    self.to_bytes_impl()
+  }
+
+  pub fn from_bytes(key: &[u8], bytes: &[u8]) -> Result<GolombFilter> {
+    GolombFilter::from_bytes_impl(key, bytes)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{OpCodes, ScriptBuilder};
+
+  /**
+   * The canonical SipHash-2-4 test vectors: key bytes 0x00..0x0f and messages of
+   * length 0, 1, 2, ... holding the bytes 0x00, 0x01, ... - https://github.com/veorq/SipHash/blob/master/vectors.h
+   */
+  #[test]
+  fn sip_hash_2_4_matches_known_answer_test_vectors() {
+    let key: [u8; 16] = (0..16).collect::<Vec<u8>>().try_into().unwrap();
+
+    let vectors: [u64; 3] = [0x726fdb47dd0e0e31, 0x74f839c593dc67fd, 0x0d6c8009d9a94f5a];
+
+    for (len, expected) in vectors.iter().enumerate() {
+      let data: Vec<u8> = (0..len as u8).collect();
+      assert_eq!(sip_hash_2_4(&key, &data), *expected, "mismatch for {}-byte message", len);
+    }
+  }
+
+  fn dummy_script(tag: u8) -> Script {
+    ScriptBuilder::new().push_opcode(OpCodes::OP_RETURN).push_slice(&[tag]).into_script()
+  }
+
+  #[test]
+  fn to_bytes_from_bytes_round_trips() {
+    let key = [7u8; 16];
+    let scripts = vec![dummy_script(1), dummy_script(2), dummy_script(3)];
+
+    let filter = GolombFilter::from_scripts_impl(&key, &scripts).unwrap();
+    let bytes = filter.to_bytes_impl().unwrap();
+    let decoded = GolombFilter::from_bytes_impl(&key, &bytes).unwrap();
+
+    assert_eq!(decoded.n, filter.n);
+    assert_eq!(decoded.filter_data, filter.filter_data);
+
+    for script in &scripts {
+      assert!(decoded.match_single_impl(script));
+    }
+  }
+
+  #[test]
+  fn match_single_distinguishes_members_from_non_members() {
+    let key = [3u8; 16];
+    let members = vec![dummy_script(10), dummy_script(20), dummy_script(30)];
+
+    let filter = GolombFilter::from_scripts_impl(&key, &members).unwrap();
+
+    for member in &members {
+      assert!(filter.match_single_impl(member));
+    }
+
+    assert!(!filter.match_single_impl(&dummy_script(99)));
+  }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl GolombFilter {
+  #[wasm_bindgen(js_name = fromScripts)]
+  pub fn from_scripts(key: &[u8], scripts: Vec<Script>) -> Result<GolombFilter, JsValue> {
+    match GolombFilter::from_scripts_impl(key, &scripts) {
+      Ok(v) => Ok(v),
+      Err(e) => throw_str(&e.to_string()),
+    }
+  }
+
+  #[wasm_bindgen(js_name = matchAny)]
+  pub fn match_any(&self, scripts: Vec<Script>) -> bool {
+    self.match_any_impl(&scripts)
+  }
+
+  #[wasm_bindgen(js_name = matchSingle)]
+  pub fn match_single(&self, script: &Script) -> bool {
+    self.match_single_impl(script)
+  }
+
+  #[wasm_bindgen(js_name = toBytes)]
+  pub fn to_bytes(&self) -> Result<Vec<u8>, JsValue> {
+    match self.to_bytes_impl() {
+      Ok(v) => Ok(v),
+      Err(e) => throw_str(&e.to_string()),
+    }
+  }
+
+  #[wasm_bindgen(js_name = fromBytes)]
+  pub fn from_bytes(key: &[u8], bytes: &[u8]) -> Result<GolombFilter, JsValue> {
+    match GolombFilter::from_bytes_impl(key, bytes) {
+      Ok(v) => Ok(v),
+      Err(e) => throw_str(&e.to_string()),
+    }
+  }
+}
@@ -0,0 +1,628 @@
+use crate::script_num::encode_minimal_script_num;
+use crate::{Hash, OpCodes, PublicKey, Script, ScriptBit, SigHash, Signature, Transaction};
+use num_traits::FromPrimitive;
+use thiserror::Error;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{prelude::*, throw_str};
+
+/**
+ * Maximum number of bytes a single stack element (push) may contain.
+ * https://github.com/bitcoin-sv/bitcoin-sv/blob/master/src/script/script.h
+ */
+const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+
+/**
+ * CScriptNum only ever operates on operands up to 4 bytes - anything bigger is a
+ * consensus error, not a bigger number.
+ */
+const MAX_SCRIPT_NUM_SIZE: usize = 4;
+
+#[derive(Debug, Error)]
+pub enum InterpreterError {
+  #[error("Attempted to pop from an empty stack")]
+  StackUnderflow,
+
+  #[error("script_sig may only contain push operations")]
+  ScriptSigNotPushOnly,
+
+  #[error("Push of {0} bytes exceeds the {1} byte element limit")]
+  PushSizeExceeded(usize, usize),
+
+  #[error("Push was not minimally encoded")]
+  NonMinimalPush,
+
+  #[error("Unbalanced OP_IF/OP_NOTIF/OP_ELSE/OP_ENDIF")]
+  UnbalancedConditional,
+
+  #[error("OP_VERIFY failed, top of stack was false")]
+  VerifyFailed,
+
+  #[error("OP_CHECKSIGVERIFY/OP_CHECKMULTISIGVERIFY failed")]
+  CheckSigVerifyFailed,
+
+  #[error("OP_RETURN encountered")]
+  EarlyReturn,
+
+  #[error("Final stack is empty")]
+  EmptyFinalStack,
+
+  #[error("Top of final stack is false")]
+  ScriptEvaluatedFalse,
+
+  #[error("Stack was not clean at the end of execution")]
+  CleanStackViolation,
+
+  #[error("Unknown SIGHASH type byte {0:#x} on signature")]
+  UnknownSigHashType(u8),
+
+  #[error("Opcode {0:?} is not yet implemented by the interpreter")]
+  UnimplementedOpCode(OpCodes),
+
+  #[error("Numeric operand is {0} bytes, exceeding the {1} byte limit")]
+  ScriptNumOverflow(usize, usize),
+
+  #[error("{0}")]
+  Other(#[from] anyhow::Error),
+}
+
+type InterpreterResult<T> = Result<T, InterpreterError>;
+
+/**
+ * A simple value stack used by the interpreter. Every element is a raw byte buffer;
+ * booleans and script numbers are encoded/decoded on the way in and out.
+ */
+#[derive(Debug, Clone, Default)]
+struct Stack(Vec<Vec<u8>>);
+
+impl Stack {
+  fn new() -> Self {
+    Stack(vec![])
+  }
+
+  fn push(&mut self, item: Vec<u8>) {
+    self.0.push(item);
+  }
+
+  fn pop(&mut self) -> InterpreterResult<Vec<u8>> {
+    self.0.pop().ok_or(InterpreterError::StackUnderflow)
+  }
+
+  fn last(&self) -> InterpreterResult<&Vec<u8>> {
+    self.0.last().ok_or(InterpreterError::StackUnderflow)
+  }
+
+  fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  fn push_bool(&mut self, value: bool) {
+    self.push(if value { vec![0x01] } else { vec![] });
+  }
+
+  fn pop_bool(&mut self) -> InterpreterResult<bool> {
+    Ok(cast_to_bool(&self.pop()?))
+  }
+
+  fn push_int(&mut self, value: i64) {
+    self.push(encode_minimal_script_num(value));
+  }
+
+  fn pop_int(&mut self) -> InterpreterResult<i64> {
+    decode_script_num(&self.pop()?)
+  }
+}
+
+fn cast_to_bool(buf: &[u8]) -> bool {
+  for (i, byte) in buf.iter().enumerate() {
+    if *byte != 0 {
+      // Negative zero (0x80 as the final byte) is still falsy
+      if i == buf.len() - 1 && *byte == 0x80 {
+        return false;
+      }
+      return true;
+    }
+  }
+  false
+}
+
+fn decode_script_num(buf: &[u8]) -> InterpreterResult<i64> {
+  if buf.len() > MAX_SCRIPT_NUM_SIZE {
+    return Err(InterpreterError::ScriptNumOverflow(buf.len(), MAX_SCRIPT_NUM_SIZE));
+  }
+
+  if buf.is_empty() {
+    return Ok(0);
+  }
+
+  let mut result: i64 = 0;
+  for (i, byte) in buf.iter().enumerate() {
+    result |= (*byte as i64) << (8 * i);
+  }
+
+  let last = buf[buf.len() - 1];
+  if last & 0x80 != 0 {
+    result &= !(0x80i64 << (8 * (buf.len() - 1)));
+    result = -result;
+  }
+
+  Ok(result)
+}
+
+fn is_minimal_push(bit: &ScriptBit) -> bool {
+  match bit {
+    // A single byte in 1..=16 must be pushed via OP_1..OP_16, and 0x81 must be pushed via
+    // OP_1NEGATE - both have dedicated opcodes and a direct push of them is non-minimal.
+    // Every other push up to the 75 byte direct-push limit (including 0x00, which is a
+    // legitimate minimal push distinct from OP_0) is fine.
+    ScriptBit::Push(data) => match data.len() {
+      1 if (1..=16).contains(&data[0]) => false,
+      1 if data[0] == 0x81 => false,
+      len => len <= 75,
+    },
+    ScriptBit::PushData(op, data) => crate::VarInt::get_pushdata_opcode(data.len() as u64) == Some(*op),
+    ScriptBit::OpCode(_) => true,
+  }
+}
+
+fn is_push_only_bit(bit: &ScriptBit) -> bool {
+  match bit {
+    ScriptBit::Push(_) | ScriptBit::PushData(_, _) => true,
+    ScriptBit::OpCode(op) => {
+      let code = *op as u32;
+      code <= OpCodes::OP_16 as u32
+    }
+  }
+}
+
+/**
+ * Evaluates Scripts against a spending Transaction. Stateless - every call
+ * to `eval` starts with a fresh main and alt stack.
+ */
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct Interpreter;
+
+impl Interpreter {
+  /**
+   * Runs script_sig then script_pubkey against the given transaction input and
+   * returns whether the pair validates. Mirrors the consensus rules that
+   * `Transaction::sighash_impl` already encodes for SIGHASH_SINGLE.
+   */
+  pub fn eval_impl(script_sig: &Script, script_pubkey: &Script, tx: &mut Transaction, n_tx_in: usize, value: u64) -> InterpreterResult<bool> {
+    if !script_sig.0.iter().all(is_push_only_bit) {
+      return Err(InterpreterError::ScriptSigNotPushOnly);
+    }
+
+    let mut stack = Stack::new();
+    let mut alt_stack = Stack::new();
+
+    Interpreter::run(script_sig, &mut stack, &mut alt_stack, tx, n_tx_in, value)?;
+    Interpreter::run(script_pubkey, &mut stack, &mut alt_stack, tx, n_tx_in, value)?;
+
+    if stack.is_empty() {
+      return Err(InterpreterError::EmptyFinalStack);
+    }
+
+    if !stack.pop_bool()? {
+      return Err(InterpreterError::ScriptEvaluatedFalse);
+    }
+
+    if !stack.is_empty() {
+      return Err(InterpreterError::CleanStackViolation);
+    }
+
+    Ok(true)
+  }
+
+  fn run(script: &Script, stack: &mut Stack, alt_stack: &mut Stack, tx: &mut Transaction, n_tx_in: usize, value: u64) -> InterpreterResult<()> {
+    let mut last_code_separator = 0usize;
+    let mut exec_stack: Vec<bool> = vec![];
+
+    for (i, bit) in script.0.iter().enumerate() {
+      let executing = exec_stack.iter().all(|x| *x);
+
+      match bit {
+        ScriptBit::Push(data) | ScriptBit::PushData(_, data) => {
+          if data.len() > MAX_SCRIPT_ELEMENT_SIZE {
+            return Err(InterpreterError::PushSizeExceeded(data.len(), MAX_SCRIPT_ELEMENT_SIZE));
+          }
+
+          if !is_minimal_push(bit) {
+            return Err(InterpreterError::NonMinimalPush);
+          }
+
+          if executing {
+            stack.push(data.clone());
+          }
+
+          continue;
+        }
+        ScriptBit::OpCode(op) => {
+          if !executing && !matches!(op, OpCodes::OP_IF | OpCodes::OP_NOTIF | OpCodes::OP_ELSE | OpCodes::OP_ENDIF) {
+            continue;
+          }
+
+          Interpreter::exec_opcode(*op, script, i, &mut last_code_separator, stack, alt_stack, &mut exec_stack, tx, n_tx_in, value)?;
+        }
+      }
+    }
+
+    if !exec_stack.is_empty() {
+      return Err(InterpreterError::UnbalancedConditional);
+    }
+
+    Ok(())
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  fn exec_opcode(
+    op: OpCodes,
+    script: &Script,
+    index: usize,
+    last_code_separator: &mut usize,
+    stack: &mut Stack,
+    alt_stack: &mut Stack,
+    exec_stack: &mut Vec<bool>,
+    tx: &mut Transaction,
+    n_tx_in: usize,
+    value: u64,
+  ) -> InterpreterResult<()> {
+    match op {
+      OpCodes::OP_0 => stack.push(vec![]),
+      OpCodes::OP_1NEGATE => stack.push_int(-1),
+      v if (v as u32) >= OpCodes::OP_1 as u32 && (v as u32) <= OpCodes::OP_16 as u32 => {
+        stack.push_int((v as u32 - OpCodes::OP_1 as u32 + 1) as i64)
+      }
+
+      OpCodes::OP_NOP => {}
+
+      OpCodes::OP_IF | OpCodes::OP_NOTIF => {
+        let parent_executing = exec_stack.iter().all(|x| *x);
+        let cond = if parent_executing { stack.pop_bool()? } else { false };
+        let branch_taken = if op == OpCodes::OP_IF { cond } else { !cond };
+        exec_stack.push(parent_executing && branch_taken);
+      }
+      OpCodes::OP_ELSE => {
+        let top = exec_stack.last_mut().ok_or(InterpreterError::UnbalancedConditional)?;
+        *top = !*top;
+      }
+      OpCodes::OP_ENDIF => {
+        exec_stack.pop().ok_or(InterpreterError::UnbalancedConditional)?;
+      }
+
+      OpCodes::OP_VERIFY => {
+        if !stack.pop_bool()? {
+          return Err(InterpreterError::VerifyFailed);
+        }
+      }
+      OpCodes::OP_RETURN => return Err(InterpreterError::EarlyReturn),
+
+      OpCodes::OP_TOALTSTACK => {
+        let item = stack.pop()?;
+        alt_stack.push(item);
+      }
+      OpCodes::OP_FROMALTSTACK => {
+        let item = alt_stack.pop()?;
+        stack.push(item);
+      }
+      OpCodes::OP_DROP => {
+        stack.pop()?;
+      }
+      OpCodes::OP_DUP => {
+        let item = stack.last()?.clone();
+        stack.push(item);
+      }
+      OpCodes::OP_SWAP => {
+        let a = stack.pop()?;
+        let b = stack.pop()?;
+        stack.push(a);
+        stack.push(b);
+      }
+      OpCodes::OP_OVER => {
+        let len = stack.0.len();
+        let item = stack.0.get(len.wrapping_sub(2)).cloned().ok_or(InterpreterError::StackUnderflow)?;
+        stack.push(item);
+      }
+      OpCodes::OP_DEPTH => {
+        let depth = stack.0.len() as i64;
+        stack.push_int(depth);
+      }
+      OpCodes::OP_SIZE => {
+        let len = stack.last()?.len() as i64;
+        stack.push_int(len);
+      }
+
+      OpCodes::OP_EQUAL | OpCodes::OP_EQUALVERIFY => {
+        let a = stack.pop()?;
+        let b = stack.pop()?;
+        let result = a == b;
+
+        if op == OpCodes::OP_EQUALVERIFY {
+          if !result {
+            return Err(InterpreterError::VerifyFailed);
+          }
+        } else {
+          stack.push_bool(result);
+        }
+      }
+
+      OpCodes::OP_1ADD => {
+        let v = stack.pop_int()?;
+        stack.push_int(v + 1);
+      }
+      OpCodes::OP_1SUB => {
+        let v = stack.pop_int()?;
+        stack.push_int(v - 1);
+      }
+      OpCodes::OP_NEGATE => {
+        let v = stack.pop_int()?;
+        stack.push_int(-v);
+      }
+      OpCodes::OP_ABS => {
+        let v = stack.pop_int()?;
+        stack.push_int(v.abs());
+      }
+      OpCodes::OP_NOT => {
+        let v = stack.pop_int()?;
+        stack.push_int(if v == 0 { 1 } else { 0 });
+      }
+      OpCodes::OP_ADD => {
+        let b = stack.pop_int()?;
+        let a = stack.pop_int()?;
+        stack.push_int(a + b);
+      }
+      OpCodes::OP_SUB => {
+        let b = stack.pop_int()?;
+        let a = stack.pop_int()?;
+        stack.push_int(a - b);
+      }
+      OpCodes::OP_BOOLAND => {
+        let b = stack.pop_int()?;
+        let a = stack.pop_int()?;
+        stack.push_bool(a != 0 && b != 0);
+      }
+      OpCodes::OP_BOOLOR => {
+        let b = stack.pop_int()?;
+        let a = stack.pop_int()?;
+        stack.push_bool(a != 0 || b != 0);
+      }
+      OpCodes::OP_NUMEQUAL | OpCodes::OP_NUMEQUALVERIFY => {
+        let b = stack.pop_int()?;
+        let a = stack.pop_int()?;
+        let result = a == b;
+
+        if op == OpCodes::OP_NUMEQUALVERIFY {
+          if !result {
+            return Err(InterpreterError::VerifyFailed);
+          }
+        } else {
+          stack.push_bool(result);
+        }
+      }
+      OpCodes::OP_LESSTHAN => {
+        let b = stack.pop_int()?;
+        let a = stack.pop_int()?;
+        stack.push_bool(a < b);
+      }
+      OpCodes::OP_GREATERTHAN => {
+        let b = stack.pop_int()?;
+        let a = stack.pop_int()?;
+        stack.push_bool(a > b);
+      }
+      OpCodes::OP_MIN => {
+        let b = stack.pop_int()?;
+        let a = stack.pop_int()?;
+        stack.push_int(a.min(b));
+      }
+      OpCodes::OP_MAX => {
+        let b = stack.pop_int()?;
+        let a = stack.pop_int()?;
+        stack.push_int(a.max(b));
+      }
+
+      OpCodes::OP_RIPEMD160 => {
+        let item = stack.pop()?;
+        stack.push(Hash::ripemd160(&item).to_bytes());
+      }
+      OpCodes::OP_SHA1 => {
+        let item = stack.pop()?;
+        stack.push(Hash::sha_1(&item).to_bytes());
+      }
+      OpCodes::OP_SHA256 => {
+        let item = stack.pop()?;
+        stack.push(Hash::sha_256(&item).to_bytes());
+      }
+      OpCodes::OP_HASH160 => {
+        let item = stack.pop()?;
+        stack.push(Hash::hash_160(&item).to_bytes());
+      }
+      OpCodes::OP_HASH256 => {
+        let item = stack.pop()?;
+        stack.push(Hash::sha_256d(&item).to_bytes());
+      }
+
+      OpCodes::OP_CODESEPARATOR => {
+        *last_code_separator = index + 1;
+      }
+
+      OpCodes::OP_CHECKSIG | OpCodes::OP_CHECKSIGVERIFY => {
+        let pub_key_buf = stack.pop()?;
+        let sig_buf = stack.pop()?;
+        let result = Interpreter::check_sig(&sig_buf, &pub_key_buf, script, *last_code_separator, tx, n_tx_in, value)?;
+
+        if op == OpCodes::OP_CHECKSIGVERIFY {
+          if !result {
+            return Err(InterpreterError::CheckSigVerifyFailed);
+          }
+        } else {
+          stack.push_bool(result);
+        }
+      }
+
+      OpCodes::OP_CHECKMULTISIG | OpCodes::OP_CHECKMULTISIGVERIFY => {
+        let result = Interpreter::check_multi_sig(stack, script, *last_code_separator, tx, n_tx_in, value)?;
+
+        if op == OpCodes::OP_CHECKMULTISIGVERIFY {
+          if !result {
+            return Err(InterpreterError::CheckSigVerifyFailed);
+          }
+        } else {
+          stack.push_bool(result);
+        }
+      }
+
+      OpCodes::OP_NOP1
+      | OpCodes::OP_NOP4
+      | OpCodes::OP_NOP5
+      | OpCodes::OP_NOP6
+      | OpCodes::OP_NOP7
+      | OpCodes::OP_NOP8
+      | OpCodes::OP_NOP9
+      | OpCodes::OP_NOP10 => {}
+
+      other => return Err(InterpreterError::UnimplementedOpCode(other)),
+    }
+
+    Ok(())
+  }
+
+  /**
+   * The subscript for a signature check is everything in the currently executing
+   * script from the last OP_CODESEPARATOR (inclusive of the index passed in) onward.
+   */
+  fn subscript(script: &Script, from_index: usize) -> Script {
+    Script(script.0[from_index.min(script.0.len())..].to_vec())
+  }
+
+  fn check_sig(sig_buf: &[u8], pub_key_buf: &[u8], script: &Script, last_code_separator: usize, tx: &mut Transaction, n_tx_in: usize, value: u64) -> InterpreterResult<bool> {
+    if sig_buf.is_empty() {
+      return Ok(false);
+    }
+
+    let (der_sig, sighash_byte) = sig_buf.split_at(sig_buf.len() - 1);
+    let sighash = match SigHash::from_u8(sighash_byte[0]) {
+      Some(v) => v,
+      None => return Err(InterpreterError::UnknownSigHashType(sighash_byte[0])),
+    };
+
+    let signature = match Signature::from_der_bytes_impl(der_sig) {
+      Ok(v) => v,
+      Err(_) => return Ok(false),
+    };
+
+    let pub_key = match PublicKey::from_bytes_impl(pub_key_buf) {
+      Ok(v) => v,
+      Err(_) => return Ok(false),
+    };
+
+    let subscript = Interpreter::subscript(script, last_code_separator);
+    let message = tx.sighash_impl(n_tx_in, sighash, &subscript, value)?;
+
+    Ok(signature.verify_impl(&message, &pub_key).unwrap_or(false))
+  }
+
+  fn check_multi_sig(stack: &mut Stack, script: &Script, last_code_separator: usize, tx: &mut Transaction, n_tx_in: usize, value: u64) -> InterpreterResult<bool> {
+    let key_count = stack.pop_int()?;
+    let mut pub_keys = vec![];
+    for _ in 0..key_count {
+      pub_keys.push(stack.pop()?);
+    }
+    pub_keys.reverse();
+
+    let sig_count = stack.pop_int()?;
+    let mut sigs = vec![];
+    for _ in 0..sig_count {
+      sigs.push(stack.pop()?);
+    }
+    sigs.reverse();
+
+    // Well-known OP_CHECKMULTISIG off-by-one bug: an extra unused item is popped.
+    stack.pop()?;
+
+    let mut key_index = 0usize;
+    for sig in sigs.iter() {
+      let mut matched = false;
+
+      while key_index < pub_keys.len() {
+        let is_match = Interpreter::check_sig(sig, &pub_keys[key_index], script, last_code_separator, tx, n_tx_in, value)?;
+        key_index += 1;
+
+        if is_match {
+          matched = true;
+          break;
+        }
+      }
+
+      if !matched {
+        return Ok(false);
+      }
+    }
+
+    Ok(true)
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Interpreter {
+  pub fn eval(script_sig: &Script, script_pubkey: &Script, tx: &mut Transaction, n_tx_in: usize, value: u64) -> Result<bool, InterpreterError> {
+    Interpreter::eval_impl(script_sig, script_pubkey, tx, n_tx_in, value)
+  }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl Interpreter {
+  #[wasm_bindgen(js_name = eval)]
+  pub fn eval(script_sig: &Script, script_pubkey: &Script, tx: &mut Transaction, n_tx_in: usize, value: u64) -> Result<bool, JsValue> {
+    match Interpreter::eval_impl(script_sig, script_pubkey, tx, n_tx_in, value) {
+      Ok(v) => Ok(v),
+      Err(e) => throw_str(&e.to_string()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{PrivateKey, ScriptBuilder, TxIn, TxOut};
+
+  #[test]
+  fn rejects_non_minimal_single_byte_pushes() {
+    // 1..=16 must be pushed via OP_1..OP_16, not a direct single-byte push.
+    assert!(!is_minimal_push(&ScriptBit::Push(vec![0x05])));
+
+    // 0x81 must be pushed via OP_1NEGATE.
+    assert!(!is_minimal_push(&ScriptBit::Push(vec![0x81])));
+
+    // 0x00 is a legitimate minimal direct push, distinct from OP_0.
+    assert!(is_minimal_push(&ScriptBit::Push(vec![0x00])));
+
+    // Anything else up to the 75 byte direct-push limit is fine.
+    assert!(is_minimal_push(&ScriptBit::Push(vec![0x11])));
+    assert!(is_minimal_push(&ScriptBit::Push(vec![0x01, 0x02])));
+  }
+
+  #[test]
+  fn rejects_oversized_arithmetic_operands_instead_of_overflowing() {
+    let mut stack = Stack::new();
+    stack.push(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+    assert!(matches!(stack.pop_int(), Err(InterpreterError::ScriptNumOverflow(9, MAX_SCRIPT_NUM_SIZE))));
+  }
+
+  #[test]
+  fn sign_then_eval_p2pkh_round_trip() {
+    let priv_key = PrivateKey::from_bytes(&[7u8; 32]).unwrap();
+    let pub_key = priv_key.to_public_key().unwrap();
+    let pub_key_hash = Hash::hash_160(&pub_key.to_bytes_impl()).to_bytes();
+
+    let script_pubkey = ScriptBuilder::new_p2pkh(&pub_key_hash);
+
+    let mut tx = Transaction::new(1, vec![TxIn::default()], vec![TxOut::default()], 0);
+
+    let sig_bytes = tx.sign(&priv_key, SigHash::InputsOutputs, 0, &script_pubkey, 1_000).unwrap();
+    let script_sig = ScriptBuilder::new().push_slice(&sig_bytes).push_key(&pub_key).into_script();
+
+    assert!(Interpreter::eval(&script_sig, &script_pubkey, &mut tx, 0, 1_000).unwrap());
+  }
+}
@@ -0,0 +1,93 @@
+use crate::script_num::encode_minimal_script_num;
+use crate::{OpCodes, PublicKey, Script, ScriptBit, VarInt};
+use num_traits::FromPrimitive;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+/**
+ * Fluent builder for assembling a `Script` one `ScriptBit` at a time. Every method
+ * consumes and returns `self` so calls can be chained into a single expression, e.g.
+ *
+ * `ScriptBuilder::new().push_opcode(OpCodes::OP_DUP).push_opcode(OpCodes::OP_HASH160)...`
+ */
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Default)]
+pub struct ScriptBuilder(Vec<ScriptBit>);
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl ScriptBuilder {
+  #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+  pub fn new() -> ScriptBuilder {
+    ScriptBuilder(vec![])
+  }
+
+  /**
+   * Appends a single, argument-less opcode.
+   */
+  pub fn push_opcode(mut self, op_code: OpCodes) -> ScriptBuilder {
+    self.0.push(ScriptBit::OpCode(op_code));
+    self
+  }
+
+  /**
+   * Appends a data push, choosing a direct push or OP_PUSHDATA1/2/4 the same way
+   * `VarInt::get_pushdata_opcode` is used elsewhere in the crate. Small values are
+   * always pushed as data, never as OP_0/OP_1..OP_16 - use `push_int` for those.
+   */
+  pub fn push_slice(mut self, data: &[u8]) -> ScriptBuilder {
+    let bit = match VarInt::get_pushdata_opcode(data.len() as u64) {
+      Some(op_code) => ScriptBit::PushData(op_code, data.to_vec()),
+      None => ScriptBit::Push(data.to_vec()),
+    };
+
+    self.0.push(bit);
+    self
+  }
+
+  /**
+   * Appends a minimally-encoded scriptnum, preferring OP_0/OP_1NEGATE/OP_1..OP_16
+   * over a data push when possible.
+   */
+  pub fn push_int(mut self, value: i64) -> ScriptBuilder {
+    match value {
+      0 => self.0.push(ScriptBit::OpCode(OpCodes::OP_0)),
+      -1 => self.0.push(ScriptBit::OpCode(OpCodes::OP_1NEGATE)),
+      1..=16 => self.0.push(ScriptBit::OpCode(OpCodes::from_u8(80 + value as u8).unwrap())),
+      _ => return self.push_slice(&encode_minimal_script_num(value)),
+    }
+
+    self
+  }
+
+  /**
+   * Appends a public key as a data push.
+   */
+  pub fn push_key(self, pub_key: &PublicKey) -> ScriptBuilder {
+    self.push_slice(&pub_key.to_bytes_impl())
+  }
+
+  pub fn into_script(self) -> Script {
+    Script(self.0)
+  }
+
+  /**
+   * `OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY OP_CHECKSIG`
+   */
+  pub fn new_p2pkh(hash: &[u8]) -> Script {
+    ScriptBuilder::new()
+      .push_opcode(OpCodes::OP_DUP)
+      .push_opcode(OpCodes::OP_HASH160)
+      .push_slice(hash)
+      .push_opcode(OpCodes::OP_EQUALVERIFY)
+      .push_opcode(OpCodes::OP_CHECKSIG)
+      .into_script()
+  }
+
+  /**
+   * `<pub_key> OP_CHECKSIG`
+   */
+  pub fn new_p2pk(pub_key: &PublicKey) -> Script {
+    ScriptBuilder::new().push_key(pub_key).push_opcode(OpCodes::OP_CHECKSIG).into_script()
+  }
+}
@@ -13,12 +13,15 @@ use wasm_bindgen::{prelude::*, throw_str};
 
 #[derive(Debug, Error)]
 pub enum ScriptTemplateErrors {
-    #[error("Script did not match template at index {0}. {2} is not equal to {1:?}")]
-    MatchFailure(usize, MatchToken, ScriptBit),
+    #[error("Script did not match template")]
+    NoMatch,
 
     #[error("Failed to parse OP_DATA code {0}: {1}")]
     OpDataParse(String, String),
 
+    #[error("Failed to parse repeat count {0}: {1}")]
+    RepeatCountParse(String, String),
+
     #[error("Script is empty but template is not.")]
     EmptyScriptDoesntMatch,
 
@@ -53,6 +56,10 @@ pub enum MatchToken {
     Signature,
     PublicKey,
     PublicKeyHash,
+
+    // Quantifiers, wrapping any of the tokens above
+    Repeat(Box<MatchToken>, usize, usize),
+    Optional(Box<MatchToken>),
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
@@ -68,12 +75,57 @@ pub enum MatchDataTypes {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Match(MatchDataTypes, Vec<u8>);
 
+impl Match {
+    pub fn data_type(&self) -> &MatchDataTypes {
+        &self.0
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.1
+    }
+}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[derive(Debug, Clone)]
 pub struct ScriptTemplate(Vec<MatchToken>);
 
 impl ScriptTemplate {
     fn map_string_to_match_token(code: &str) -> Result<MatchToken, ScriptTemplateErrors> {
+        // Quantifier suffixes - parsed first so e.g. "OP_DATA*" recurses into the
+        // base token "OP_DATA" and wraps it rather than falling through to the
+        // OP_DATA length-constraint or raw hex branches below.
+        if let Some(base) = code.strip_suffix('*') {
+            let inner = ScriptTemplate::map_string_to_match_token(base)?;
+            return Ok(MatchToken::Repeat(Box::new(inner), 0, usize::MAX));
+        }
+
+        if let Some(base) = code.strip_suffix('+') {
+            let inner = ScriptTemplate::map_string_to_match_token(base)?;
+            return Ok(MatchToken::Repeat(Box::new(inner), 1, usize::MAX));
+        }
+
+        if let Some(base) = code.strip_suffix('?') {
+            let inner = ScriptTemplate::map_string_to_match_token(base)?;
+            return Ok(MatchToken::Optional(Box::new(inner)));
+        }
+
+        if let Some(base) = code.strip_suffix('}') {
+            if let Some(open_brace) = base.rfind('{') {
+                let range_str = &base[open_brace + 1..];
+                let base = &base[..open_brace];
+
+                let (min_str, max_str) = range_str
+                    .split_once(',')
+                    .ok_or_else(|| ScriptTemplateErrors::RepeatCountParse(range_str.to_string(), "expected {min,max}".to_string()))?;
+
+                let min = usize::from_str(min_str).map_err(|e| ScriptTemplateErrors::RepeatCountParse(range_str.to_string(), e.to_string()))?;
+                let max = usize::from_str(max_str).map_err(|e| ScriptTemplateErrors::RepeatCountParse(range_str.to_string(), e.to_string()))?;
+                let inner = ScriptTemplate::map_string_to_match_token(base)?;
+
+                return Ok(MatchToken::Repeat(Box::new(inner), min, max));
+            }
+        }
+
         // Number OP_CODES
         if let Ok(num_code) = u8::from_str(code) {
             match num_code {
@@ -177,68 +229,143 @@ impl ScriptTemplate {
 }
 
 /**
- * Script Template
+ * Does a single (non-quantifier) template token match this script bit, and if so what
+ * data - if any - should be captured into the returned `Match` list.
  */
-impl Script {
-    pub fn match_impl(&self, script_template: &ScriptTemplate) -> Result<Vec<Match>, ScriptTemplateErrors> {
-        if self.0.is_empty() && !script_template.0.is_empty() {
-            return Err(ScriptTemplateErrors::EmptyScriptDoesntMatch);
-        }
-
-        let mut matches = vec![];
+fn match_single_token(template: &MatchToken, script: &ScriptBit) -> Option<Option<Match>> {
+    match (template, script) {
+        (MatchToken::OpCode(tmpl_code), ScriptBit::OpCode(op_code)) => (tmpl_code == op_code).then_some(None),
+        (MatchToken::Push(tmpl_data), ScriptBit::Push(data)) => (tmpl_data == data).then_some(None),
+        (MatchToken::PushData(tmpl_op, tmpl_data), ScriptBit::PushData(op, data)) => (tmpl_op == op && tmpl_data == data).then_some(None),
+
+        (MatchToken::Data(len, constraint), ScriptBit::PushData(_, data) | ScriptBit::Push(data)) => {
+            let matches = match constraint {
+                DataLengthConstraints::Equals => &data.len() == len,
+                DataLengthConstraints::GreaterThan => &data.len() > len,
+                DataLengthConstraints::LessThan => &data.len() < len,
+                DataLengthConstraints::GreaterThanOrEquals => &data.len() >= len,
+                DataLengthConstraints::LessThanOrEquals => &data.len() <= len,
+            };
 
-        for (i, (template, script)) in script_template.0.iter().zip(self.0.iter()).enumerate() {
-            let is_match = match (template, script) {
-                (MatchToken::OpCode(tmpl_code), ScriptBit::OpCode(op_code)) => tmpl_code == op_code,
-                (MatchToken::Push(tmpl_data), ScriptBit::Push(data)) => tmpl_data == data,
-                (MatchToken::PushData(tmpl_op, tmpl_data), ScriptBit::PushData(op, data)) => tmpl_op == op && tmpl_data == data,
+            matches.then(|| Some(Match(MatchDataTypes::Data, data.clone())))
+        }
 
-                (MatchToken::Data(len, constraint), ScriptBit::PushData(_, data) | ScriptBit::Push(data)) => match constraint {
-                    DataLengthConstraints::Equals => &data.len() == len,
-                    DataLengthConstraints::GreaterThan => &data.len() > len,
-                    DataLengthConstraints::LessThan => &data.len() < len,
-                    DataLengthConstraints::GreaterThanOrEquals => &data.len() >= len,
-                    DataLengthConstraints::LessThanOrEquals => &data.len() <= len,
-                },
+        (MatchToken::AnyData, ScriptBit::Push(data) | ScriptBit::PushData(_, data)) => Some(Some(Match(MatchDataTypes::Data, data.clone()))),
 
-                (MatchToken::AnyData, ScriptBit::Push(_)) => true,
-                (MatchToken::AnyData, ScriptBit::PushData(_, _)) => true,
+        (MatchToken::Signature, ScriptBit::Push(sig_buf)) => Signature::from_compact_impl(sig_buf).is_ok().then(|| Some(Match(MatchDataTypes::Signature, sig_buf.clone()))),
 
-                (MatchToken::Signature, ScriptBit::Push(sig_buf)) => Signature::from_compact_impl(sig_buf).is_ok(),
+        (MatchToken::PublicKey, ScriptBit::Push(pubkey_buf)) => PublicKey::from_bytes_impl(pubkey_buf).is_ok().then(|| Some(Match(MatchDataTypes::PublicKey, pubkey_buf.clone()))),
 
-                (MatchToken::PublicKey, ScriptBit::Push(pubkey_buf)) => PublicKey::from_bytes_impl(pubkey_buf).is_ok(),
+        (MatchToken::PublicKeyHash, ScriptBit::Push(pubkeyhash_buf)) => (pubkeyhash_buf.len() == 20).then(|| Some(Match(MatchDataTypes::PublicKeyHash, pubkeyhash_buf.clone()))), // OP_HASH160
 
-                (MatchToken::PublicKeyHash, ScriptBit::Push(pubkeyhash_buf)) => pubkeyhash_buf.len() == 20, // OP_HASH160
+        _ => None,
+    }
+}
 
-                _ => false,
-            };
+/**
+ * Walks `tokens[ti..]` against `script[si..]` with a cursor on each side, advancing
+ * independently so `Repeat`/`Optional` tokens can consume zero, one, or many script
+ * bits. Quantifiers are greedy but backtrack if a later fixed token would otherwise
+ * fail to match - e.g. `OP_DATA* OP_PUBKEYHASH` first takes every push into `OP_DATA*`,
+ * then gives the last one back so it can line up with `OP_PUBKEYHASH`. Both cursors must be fully exhausted to match,
+ * which also fixes the old `zip`-based matcher silently accepting a longer script.
+ */
+fn solve(tokens: &[MatchToken], ti: usize, script: &[ScriptBit], si: usize) -> Option<Vec<Match>> {
+    if ti == tokens.len() {
+        return if si == script.len() { Some(vec![]) } else { None };
+    }
 
-            if !is_match {
-                return Err(ScriptTemplateErrors::MatchFailure(i, template.clone(), script.clone()));
+    match &tokens[ti] {
+        MatchToken::Repeat(inner, min, max) => {
+            // Collect the longest greedy run up front, remembering the matches captured at each count.
+            let mut runs: Vec<Vec<Match>> = vec![vec![]];
+            let mut count = 0;
+
+            while count < *max && si + count < script.len() {
+                match match_single_token(inner, &script[si + count]) {
+                    Some(captured) => {
+                        let mut next = runs[count].clone();
+                        if let Some(m) = captured {
+                            next.push(m);
+                        }
+                        runs.push(next);
+                        count += 1;
+                    }
+                    None => break,
+                }
             }
 
-            // Now that we know script bit is a match, we can add the data parts to the matches array.
-            match (template, script) {
-                (MatchToken::Data(_, _), ScriptBit::PushData(_, data) | ScriptBit::Push(data)) => matches.push(Match(MatchDataTypes::Data, data.clone())),
-
-                (MatchToken::AnyData, ScriptBit::Push(data)) => matches.push(Match(MatchDataTypes::Data, data.clone())),
-                (MatchToken::AnyData, ScriptBit::PushData(_, data)) => matches.push(Match(MatchDataTypes::Data, data.clone())),
+            // Try the longest run first, backtracking towards `min` until the remainder of the template matches.
+            (*min..=count).rev().find_map(|k| {
+                solve(tokens, ti + 1, script, si + k).map(|mut rest| {
+                    let mut result = runs[k].clone();
+                    result.append(&mut rest);
+                    result
+                })
+            })
+        }
 
-                (MatchToken::Signature, ScriptBit::Push(data)) => matches.push(Match(MatchDataTypes::Data, data.clone())),
+        MatchToken::Optional(inner) => {
+            let consumed = (si < script.len())
+                .then(|| match_single_token(inner, &script[si]))
+                .flatten()
+                .and_then(|captured| {
+                    solve(tokens, ti + 1, script, si + 1).map(|mut rest| {
+                        let mut result = vec![];
+                        if let Some(m) = captured {
+                            result.push(m);
+                        }
+                        result.append(&mut rest);
+                        result
+                    })
+                });
+
+            consumed.or_else(|| solve(tokens, ti + 1, script, si))
+        }
 
-                (MatchToken::PublicKey, ScriptBit::Push(data)) => matches.push(Match(MatchDataTypes::Data, data.clone())),
+        token => {
+            let bit = script.get(si)?;
+            let captured = match_single_token(token, bit)?;
+            let mut rest = solve(tokens, ti + 1, script, si + 1)?;
 
-                (MatchToken::PublicKeyHash, ScriptBit::Push(data)) => matches.push(Match(MatchDataTypes::Data, data.clone())), // OP_HASH160
-                _ => (),
+            let mut result = vec![];
+            if let Some(m) = captured {
+                result.push(m);
             }
+            result.append(&mut rest);
+            Some(result)
+        }
+    }
+}
+
+impl Script {
+    pub fn match_impl(&self, script_template: &ScriptTemplate) -> Result<Vec<Match>, ScriptTemplateErrors> {
+        if self.0.is_empty() && !script_template.0.is_empty() {
+            return solve(&script_template.0, 0, &self.0, 0).ok_or(ScriptTemplateErrors::EmptyScriptDoesntMatch);
         }
 
-        Ok(matches)
+        solve(&script_template.0, 0, &self.0, 0).ok_or(ScriptTemplateErrors::NoMatch)
     }
 
     pub fn test_impl(&self, script_template: &ScriptTemplate) -> bool {
         self.match_impl(script_template).is_ok()
     }
+
+    /**
+     * Every push in the script that parses as a valid `PublicKey`, in on-chain order.
+     * Unlike `match_impl`, this doesn't require a template describing the surrounding
+     * opcodes - it's for callers like a multisig finalizer that just need the redeem
+     * script's actual pubkey ordering to line signatures back up against.
+     */
+    pub(crate) fn pubkeys_in_order_impl(&self) -> Vec<PublicKey> {
+        self.0
+            .iter()
+            .filter_map(|bit| match bit {
+                ScriptBit::Push(data) | ScriptBit::PushData(_, data) => PublicKey::from_bytes_impl(data).ok(),
+                ScriptBit::OpCode(_) => None,
+            })
+            .collect()
+    }
 }
 
 // #[cfg(target_arch = "wasm32")]
@@ -278,4 +405,65 @@ impl Script {
     pub fn test(&self, script_template: &ScriptTemplate) -> bool {
         self.test_impl(script_template)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OpCodes, ScriptBuilder};
+
+    #[test]
+    fn repeat_star_matches_zero_or_more_pushes_before_a_trailing_fixed_token() {
+        let template = ScriptTemplate::from_asm_string_impl("OP_DATA* OP_RETURN").unwrap();
+
+        let empty = ScriptBuilder::new().push_opcode(OpCodes::OP_RETURN).into_script();
+        assert!(empty.test_impl(&template));
+
+        let several = ScriptBuilder::new()
+            .push_slice(&[1])
+            .push_slice(&[2, 2])
+            .push_slice(&[3, 3, 3])
+            .push_opcode(OpCodes::OP_RETURN)
+            .into_script();
+        assert!(several.test_impl(&template));
+
+        let missing_trailing_op = ScriptBuilder::new().push_slice(&[1]).into_script();
+        assert!(!missing_trailing_op.test_impl(&template));
+    }
+
+    #[test]
+    fn repeat_range_enforces_min_and_max_bounds() {
+        let template = ScriptTemplate::from_asm_string_impl("OP_DATA{2,3}").unwrap();
+
+        let one_push = ScriptBuilder::new().push_slice(&[1]).into_script();
+        assert!(!one_push.test_impl(&template));
+
+        let two_pushes = ScriptBuilder::new().push_slice(&[1]).push_slice(&[2]).into_script();
+        assert!(two_pushes.test_impl(&template));
+
+        let three_pushes = ScriptBuilder::new().push_slice(&[1]).push_slice(&[2]).push_slice(&[3]).into_script();
+        assert!(three_pushes.test_impl(&template));
+
+        let four_pushes = ScriptBuilder::new()
+            .push_slice(&[1])
+            .push_slice(&[2])
+            .push_slice(&[3])
+            .push_slice(&[4])
+            .into_script();
+        assert!(!four_pushes.test_impl(&template));
+    }
+
+    #[test]
+    fn optional_token_matches_with_or_without_the_wrapped_push() {
+        let template = ScriptTemplate::from_asm_string_impl("OP_DATA? OP_RETURN").unwrap();
+
+        let without_data = ScriptBuilder::new().push_opcode(OpCodes::OP_RETURN).into_script();
+        assert!(without_data.test_impl(&template));
+
+        let with_data = ScriptBuilder::new().push_slice(&[9]).push_opcode(OpCodes::OP_RETURN).into_script();
+        assert!(with_data.test_impl(&template));
+
+        let with_two_data_pushes = ScriptBuilder::new().push_slice(&[9]).push_slice(&[9]).push_opcode(OpCodes::OP_RETURN).into_script();
+        assert!(!with_two_data_pushes.test_impl(&template));
+    }
 }
\ No newline at end of file
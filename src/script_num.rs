@@ -0,0 +1,29 @@
+/**
+ * Minimal scriptnum encoding (little-endian magnitude, sign bit in the MSB of the
+ * final byte) shared by the interpreter (stack `OP_1ADD`/`OP_ADD`/... results) and
+ * `ScriptBuilder::push_int` (values outside the OP_0/OP_1NEGATE/OP_1..OP_16 range) -
+ * both need the exact same CScriptNum-compatible encoding.
+ */
+pub(crate) fn encode_minimal_script_num(value: i64) -> Vec<u8> {
+  if value == 0 {
+    return vec![];
+  }
+
+  let negative = value < 0;
+  let mut abs_value = value.unsigned_abs();
+  let mut result = vec![];
+
+  while abs_value > 0 {
+    result.push((abs_value & 0xff) as u8);
+    abs_value >>= 8;
+  }
+
+  if result.last().map(|b| b & 0x80 != 0).unwrap_or(false) {
+    result.push(if negative { 0x80 } else { 0x00 });
+  } else if negative {
+    let last = result.last_mut().unwrap();
+    *last |= 0x80;
+  }
+
+  result
+}
@@ -0,0 +1,428 @@
+use std::io::{Cursor, Read, Write};
+
+use crate::{transaction::*, PrivateKey, PublicKey, Script, ScriptBuilder, Signature, VarInt};
+use anyhow::*;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use num_traits::{FromPrimitive, ToPrimitive};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::throw_str;
+
+/**
+ * Per-input metadata attached by the Updater and filled in by the Signer. A half-signed
+ * `PartialTransaction` can carry zero, one, or many of these - enough for an m-of-n
+ * multisig input to collect signatures from separate wallets before finalizing.
+ */
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PartialInput {
+  pub(crate) prevout_script: Option<Script>,
+  pub(crate) value: Option<u64>,
+  pub(crate) sighash: Option<SigHash>,
+  pub(crate) signatures: Vec<(PublicKey, Signature)>,
+}
+
+/**
+ * A BIP174-style partially-signed transaction. Roles map onto methods:
+ *
+ * - Creator: `PartialTransaction::new_impl`, building the skeleton from a `Transaction`.
+ * - Updater: `update_input_impl`, attaching the prevout `Script`/`value`/`SigHash` a signer needs.
+ * - Signer: `sign_input_impl`, which reuses `Transaction::sign_impl` and records the
+ *   resulting `(PublicKey, Signature)` pair without touching the input's script_sig.
+ * - Finalizer: `finalize_p2pkh_input_impl`/`finalize_multisig_input_impl`, which assemble
+ *   the unlocking `Script` once enough signatures are present.
+ */
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct PartialTransaction {
+  pub(crate) tx: Transaction,
+  pub(crate) inputs: Vec<PartialInput>,
+}
+
+impl PartialTransaction {
+  pub fn new_impl(tx: Transaction) -> PartialTransaction {
+    let inputs = tx.get_ninputs() as usize;
+
+    PartialTransaction {
+      tx,
+      inputs: vec![PartialInput::default(); inputs],
+    }
+  }
+
+  fn input_mut(&mut self, n_tx_in: usize) -> Result<&mut PartialInput> {
+    self.inputs.get_mut(n_tx_in).ok_or(anyhow!(format!("Could not get input metadata at index {}", n_tx_in)))
+  }
+
+  fn input(&self, n_tx_in: usize) -> Result<&PartialInput> {
+    self.inputs.get(n_tx_in).ok_or(anyhow!(format!("Could not get input metadata at index {}", n_tx_in)))
+  }
+
+  pub fn update_input_impl(&mut self, n_tx_in: usize, prevout_script: &Script, value: u64, sighash: SigHash) -> Result<()> {
+    let input = self.input_mut(n_tx_in)?;
+    input.prevout_script = Some(prevout_script.clone());
+    input.value = Some(value);
+    input.sighash = Some(sighash);
+
+    Ok(())
+  }
+
+  pub fn sign_input_impl(&mut self, n_tx_in: usize, priv_key: &PrivateKey) -> Result<()> {
+    let input = self.input(n_tx_in)?.clone();
+
+    let prevout_script = input.prevout_script.ok_or(anyhow!(format!("Updater has not attached a prevout script for input {}", n_tx_in)))?;
+    let value = input.value.ok_or(anyhow!(format!("Updater has not attached a value for input {}", n_tx_in)))?;
+    let sighash = input.sighash.ok_or(anyhow!(format!("Updater has not attached a SigHash type for input {}", n_tx_in)))?;
+
+    // `Transaction`'s HashCache remembers whichever SigHash first populated it, so reusing
+    // `self.tx` across inputs that carry different SigHash types (the whole point of
+    // per-input metadata here) would silently sign later inputs against the wrong preimage.
+    // Each signing pass gets a fresh cache so it's always computed for this input's sighash.
+    self.tx.hash_cache = HashCache::default();
+    let sig_bytes = self.tx.sign_impl(priv_key, sighash, n_tx_in, &prevout_script, value)?;
+    let der_bytes = &sig_bytes[..sig_bytes.len() - 1];
+
+    let signature = Signature::from_der_bytes_impl(der_bytes)?;
+    let pub_key = priv_key.to_public_key_impl()?;
+
+    self.input_mut(n_tx_in)?.signatures.push((pub_key, signature));
+
+    Ok(())
+  }
+
+  /**
+   * Assembles `<sig> <pubkey>` once the single signature a P2PKH input needs has been collected.
+   */
+  pub fn finalize_p2pkh_input_impl(&self, n_tx_in: usize) -> Result<Script> {
+    let input = self.input(n_tx_in)?;
+    let sighash = input.sighash.ok_or(anyhow!(format!("Updater has not attached a SigHash type for input {}", n_tx_in)))?;
+    let (pub_key, signature) = input.signatures.first().ok_or(anyhow!(format!("No signatures recorded for input {}", n_tx_in)))?;
+
+    Ok(ScriptBuilder::new().push_slice(&Self::sig_push_bytes(signature, sighash)?).push_key(pub_key).into_script())
+  }
+
+  /**
+   * Assembles `OP_0 <sig1> <sig2> ...` for a bare CHECKMULTISIG input, the leading `OP_0`
+   * supplying the well-known extra dummy element that `OP_CHECKMULTISIG` pops.
+   *
+   * Signers call `sign_input_impl` independently and in no particular order (that's the
+   * point of collecting an m-of-n from separate wallets), but `OP_CHECKMULTISIG` only
+   * accepts signatures in the same ascending order as the redeem script's pubkeys. So
+   * recorded signatures are re-sorted to match the prevout script's actual pubkey order
+   * rather than emitted in insertion order.
+   */
+  pub fn finalize_multisig_input_impl(&self, n_tx_in: usize) -> Result<Script> {
+    let input = self.input(n_tx_in)?;
+    let sighash = input.sighash.ok_or(anyhow!(format!("Updater has not attached a SigHash type for input {}", n_tx_in)))?;
+    let prevout_script = input.prevout_script.as_ref().ok_or(anyhow!(format!("Updater has not attached a prevout script for input {}", n_tx_in)))?;
+
+    if input.signatures.is_empty() {
+      return Err(anyhow!(format!("No signatures recorded for input {}", n_tx_in)));
+    }
+
+    let redeem_pub_keys = prevout_script.pubkeys_in_order_impl();
+
+    let mut ordered_signatures = input.signatures.clone();
+    let mut positions = Vec::with_capacity(ordered_signatures.len());
+
+    for (pub_key, _) in ordered_signatures.iter() {
+      let position = redeem_pub_keys
+        .iter()
+        .position(|redeem_pub_key| redeem_pub_key.to_bytes_impl() == pub_key.to_bytes_impl())
+        .ok_or(anyhow!(format!("Recorded signature for input {} does not match any pubkey in the redeem script", n_tx_in)))?;
+
+      positions.push(position);
+    }
+
+    let mut indices: Vec<usize> = (0..ordered_signatures.len()).collect();
+    indices.sort_by_key(|&i| positions[i]);
+    ordered_signatures = indices.into_iter().map(|i| ordered_signatures[i].clone()).collect();
+
+    let mut builder = ScriptBuilder::new().push_int(0);
+
+    for (_, signature) in ordered_signatures.iter() {
+      builder = builder.push_slice(&Self::sig_push_bytes(signature, sighash)?);
+    }
+
+    Ok(builder.into_script())
+  }
+
+  fn sig_push_bytes(signature: &Signature, sighash: SigHash) -> Result<Vec<u8>> {
+    let mut sig_bytes = signature.to_der_bytes_impl();
+    let sighash_u8 = sighash.to_u8().ok_or(anyhow!(format!("Cannot convert SigHash {:?} into u8", sighash)))?;
+    sig_bytes.push(sighash_u8);
+
+    Ok(sig_bytes)
+  }
+
+  pub fn to_bytes_impl(&self) -> Result<Vec<u8>> {
+    let mut buffer: Vec<u8> = vec![];
+
+    let tx_bytes = self.tx.to_bytes_impl()?;
+    buffer.write_varint(tx_bytes.len() as u64)?;
+    buffer.write(&tx_bytes)?;
+
+    buffer.write_varint(self.inputs.len() as u64)?;
+
+    for input in self.inputs.iter() {
+      match &input.prevout_script {
+        Some(script) => {
+          buffer.write_u8(1)?;
+          let script_bytes = script.to_bytes();
+          buffer.write_varint(script_bytes.len() as u64)?;
+          buffer.write(&script_bytes)?;
+        }
+        None => buffer.write_u8(0)?,
+      }
+
+      match input.value {
+        Some(value) => {
+          buffer.write_u8(1)?;
+          buffer.write_u64::<LittleEndian>(value)?;
+        }
+        None => buffer.write_u8(0)?,
+      }
+
+      match input.sighash {
+        Some(sighash) => {
+          buffer.write_u8(1)?;
+          buffer.write_u8(sighash.to_u8().ok_or(anyhow!(format!("Cannot convert SigHash {:?} into u8", sighash)))?)?;
+        }
+        None => buffer.write_u8(0)?,
+      }
+
+      buffer.write_varint(input.signatures.len() as u64)?;
+
+      for (pub_key, signature) in input.signatures.iter() {
+        let pub_key_bytes = pub_key.to_bytes_impl();
+        buffer.write_varint(pub_key_bytes.len() as u64)?;
+        buffer.write(&pub_key_bytes)?;
+
+        let sig_bytes = signature.to_der_bytes_impl();
+        buffer.write_varint(sig_bytes.len() as u64)?;
+        buffer.write(&sig_bytes)?;
+      }
+    }
+
+    Ok(buffer)
+  }
+
+  pub fn from_bytes_impl(bytes: &[u8]) -> Result<PartialTransaction> {
+    let mut cursor = Cursor::new(bytes);
+
+    let tx_len = cursor.read_varint()?;
+    let mut tx_bytes = vec![0u8; tx_len as usize];
+    cursor.read_exact(&mut tx_bytes)?;
+    let tx = Transaction::from_bytes_impl(&tx_bytes)?;
+
+    let n_inputs = cursor.read_varint()?;
+    let mut inputs = vec![];
+
+    for _ in 0..n_inputs {
+      let mut input = PartialInput::default();
+
+      if cursor.read_u8()? == 1 {
+        let script_len = cursor.read_varint()?;
+        let mut script_bytes = vec![0u8; script_len as usize];
+        cursor.read_exact(&mut script_bytes)?;
+        input.prevout_script = Some(Script::from_bytes_impl(&script_bytes)?);
+      }
+
+      if cursor.read_u8()? == 1 {
+        input.value = Some(cursor.read_u64::<LittleEndian>()?);
+      }
+
+      if cursor.read_u8()? == 1 {
+        let sighash_byte = cursor.read_u8()?;
+        input.sighash = Some(SigHash::from_u8(sighash_byte).ok_or(anyhow!(format!("Unknown SigHash byte {:#x}", sighash_byte)))?);
+      }
+
+      let n_sigs = cursor.read_varint()?;
+
+      for _ in 0..n_sigs {
+        let pub_key_len = cursor.read_varint()?;
+        let mut pub_key_bytes = vec![0u8; pub_key_len as usize];
+        cursor.read_exact(&mut pub_key_bytes)?;
+
+        let sig_len = cursor.read_varint()?;
+        let mut sig_bytes = vec![0u8; sig_len as usize];
+        cursor.read_exact(&mut sig_bytes)?;
+
+        input.signatures.push((PublicKey::from_bytes_impl(&pub_key_bytes)?, Signature::from_der_bytes_impl(&sig_bytes)?));
+      }
+
+      inputs.push(input);
+    }
+
+    Ok(PartialTransaction { tx, inputs })
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PartialTransaction {
+  pub fn new(tx: Transaction) -> PartialTransaction {
+    PartialTransaction::new_impl(tx)
+  }
+
+  pub fn update_input(&mut self, n_tx_in: usize, prevout_script: &Script, value: u64, sighash: SigHash) -> Result<()> {
+    PartialTransaction::update_input_impl(self, n_tx_in, prevout_script, value, sighash)
+  }
+
+  pub fn sign_input(&mut self, n_tx_in: usize, priv_key: &PrivateKey) -> Result<()> {
+    PartialTransaction::sign_input_impl(self, n_tx_in, priv_key)
+  }
+
+  pub fn finalize_p2pkh_input(&self, n_tx_in: usize) -> Result<Script> {
+    PartialTransaction::finalize_p2pkh_input_impl(self, n_tx_in)
+  }
+
+  pub fn finalize_multisig_input(&self, n_tx_in: usize) -> Result<Script> {
+    PartialTransaction::finalize_multisig_input_impl(self, n_tx_in)
+  }
+
+  pub fn to_bytes(&self) -> Result<Vec<u8>> {
+    PartialTransaction::to_bytes_impl(self)
+  }
+
+  pub fn from_bytes(bytes: &[u8]) -> Result<PartialTransaction> {
+    PartialTransaction::from_bytes_impl(bytes)
+  }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl PartialTransaction {
+  #[wasm_bindgen(constructor)]
+  pub fn new(tx: Transaction) -> PartialTransaction {
+    PartialTransaction::new_impl(tx)
+  }
+
+  #[wasm_bindgen(js_name = updateInput)]
+  pub fn update_input(&mut self, n_tx_in: usize, prevout_script: &Script, value: u64, sighash: SigHash) -> Result<(), JsValue> {
+    match PartialTransaction::update_input_impl(self, n_tx_in, prevout_script, value, sighash) {
+      Ok(v) => Ok(v),
+      Err(e) => throw_str(&e.to_string()),
+    }
+  }
+
+  #[wasm_bindgen(js_name = signInput)]
+  pub fn sign_input(&mut self, n_tx_in: usize, priv_key: &PrivateKey) -> Result<(), JsValue> {
+    match PartialTransaction::sign_input_impl(self, n_tx_in, priv_key) {
+      Ok(v) => Ok(v),
+      Err(e) => throw_str(&e.to_string()),
+    }
+  }
+
+  #[wasm_bindgen(js_name = finalizeP2PKHInput)]
+  pub fn finalize_p2pkh_input(&self, n_tx_in: usize) -> Result<Script, JsValue> {
+    match PartialTransaction::finalize_p2pkh_input_impl(self, n_tx_in) {
+      Ok(v) => Ok(v),
+      Err(e) => throw_str(&e.to_string()),
+    }
+  }
+
+  #[wasm_bindgen(js_name = finalizeMultisigInput)]
+  pub fn finalize_multisig_input(&self, n_tx_in: usize) -> Result<Script, JsValue> {
+    match PartialTransaction::finalize_multisig_input_impl(self, n_tx_in) {
+      Ok(v) => Ok(v),
+      Err(e) => throw_str(&e.to_string()),
+    }
+  }
+
+  #[wasm_bindgen(js_name = toBytes)]
+  pub fn to_bytes(&self) -> Result<Vec<u8>, JsValue> {
+    match PartialTransaction::to_bytes_impl(self) {
+      Ok(v) => Ok(v),
+      Err(e) => throw_str(&e.to_string()),
+    }
+  }
+
+  #[wasm_bindgen(js_name = fromBytes)]
+  pub fn from_bytes(bytes: &[u8]) -> Result<PartialTransaction, JsValue> {
+    match PartialTransaction::from_bytes_impl(bytes) {
+      Ok(v) => Ok(v),
+      Err(e) => throw_str(&e.to_string()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Hash, PrivateKey, TxIn, TxOut};
+
+  fn tx_with(n_inputs: usize, n_outputs: usize) -> Transaction {
+    Transaction::new(1, vec![TxIn::default(); n_inputs], vec![TxOut::default(); n_outputs], 0)
+  }
+
+  #[test]
+  fn finalize_multisig_input_orders_signatures_by_redeem_script_pubkey_position() {
+    let priv_key_1 = PrivateKey::from_bytes(&[1u8; 32]).unwrap();
+    let priv_key_2 = PrivateKey::from_bytes(&[2u8; 32]).unwrap();
+    let priv_key_3 = PrivateKey::from_bytes(&[3u8; 32]).unwrap();
+
+    let pub_key_1 = priv_key_1.to_public_key().unwrap();
+    let pub_key_2 = priv_key_2.to_public_key().unwrap();
+    let pub_key_3 = priv_key_3.to_public_key().unwrap();
+
+    // Bare 2-of-3 multisig redeem script, pubkeys in a fixed on-chain order.
+    let redeem_script = ScriptBuilder::new()
+      .push_int(2)
+      .push_key(&pub_key_1)
+      .push_key(&pub_key_2)
+      .push_key(&pub_key_3)
+      .push_int(3)
+      .push_opcode(crate::OpCodes::OP_CHECKMULTISIG)
+      .into_script();
+
+    let value = 1_000u64;
+    let sighash = SigHash::InputsOutputs;
+
+    let mut pt = PartialTransaction::new_impl(tx_with(1, 1));
+    pt.update_input_impl(0, &redeem_script, value, sighash).unwrap();
+
+    // Signers call in arbitrary order - here the third key signs before the first.
+    pt.sign_input_impl(0, &priv_key_3).unwrap();
+    pt.sign_input_impl(0, &priv_key_1).unwrap();
+
+    let finalized = pt.finalize_multisig_input_impl(0).unwrap();
+
+    // The unlocking script must still list the signatures in redeem-script pubkey order
+    // (key 1 then key 3), not the insertion order they were signed in (key 3 then key 1).
+    let mut expected_tx = tx_with(1, 1);
+    let sig_1_bytes = expected_tx.sign_impl(&priv_key_1, sighash, 0, &redeem_script, value).unwrap();
+    let sig_3_bytes = expected_tx.sign_impl(&priv_key_3, sighash, 0, &redeem_script, value).unwrap();
+
+    let expected_script = ScriptBuilder::new().push_int(0).push_slice(&sig_1_bytes).push_slice(&sig_3_bytes).into_script();
+
+    assert_eq!(finalized.to_bytes(), expected_script.to_bytes());
+  }
+
+  #[test]
+  fn sign_input_does_not_leak_a_stale_hash_cache_across_differing_sighash_types() {
+    let priv_key = PrivateKey::from_bytes(&[9u8; 32]).unwrap();
+    let pub_key = priv_key.to_public_key().unwrap();
+    let pub_key_hash = Hash::hash_160(&pub_key.to_bytes_impl()).to_bytes();
+    let script_pubkey = ScriptBuilder::new_p2pkh(&pub_key_hash);
+
+    let mut pt = PartialTransaction::new_impl(tx_with(2, 2));
+
+    // Input 0 is signed ALL|FORKID, input 1 is signed SINGLE|FORKID - two passes over the
+    // same underlying `Transaction` with different SigHash types.
+    pt.update_input_impl(0, &script_pubkey, 1_000, SigHash::InputsOutputs).unwrap();
+    pt.update_input_impl(1, &script_pubkey, 2_000, SigHash::InputsOutput).unwrap();
+
+    pt.sign_input_impl(0, &priv_key).unwrap();
+    pt.sign_input_impl(1, &priv_key).unwrap();
+
+    let recorded_signature = &pt.input(1).unwrap().signatures[0].1;
+
+    // Ground truth: sign input 1 on a completely isolated `Transaction`, so its HashCache
+    // was never touched by input 0's ALL-type signing pass.
+    let mut isolated_tx = tx_with(2, 2);
+    let isolated_sig_bytes = isolated_tx.sign_impl(&priv_key, SigHash::InputsOutput, 1, &script_pubkey, 2_000).unwrap();
+    let isolated_der_bytes = &isolated_sig_bytes[..isolated_sig_bytes.len() - 1];
+
+    assert_eq!(recorded_signature.to_der_bytes_impl(), isolated_der_bytes);
+  }
+}
@@ -0,0 +1,93 @@
+use crate::{transaction::*, Hash, PublicKey, Script, ScriptTemplate, Signature};
+use anyhow::*;
+use num_traits::FromPrimitive;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::throw_str;
+
+impl Transaction {
+  /**
+   * Verifies a raw signature buffer (DER signature followed by the SIGHASH type byte,
+   * exactly as it would appear pushed in a script_sig) against this input. Reads the
+   * SIGHASH type off the trailing byte, rebuilds the preimage with `sighash_preimage_impl`
+   * and checks the ECDSA signature over its Sha256d hash.
+   */
+  pub(crate) fn verify_signature_impl(&mut self, n_tx_in: usize, pub_key: &PublicKey, signature: &[u8], subscript: &Script, value: u64) -> Result<bool> {
+    if signature.is_empty() {
+      return Ok(false);
+    }
+
+    let (der_sig, sighash_byte) = signature.split_at(signature.len() - 1);
+    let sighash = SigHash::from_u8(sighash_byte[0]).ok_or(anyhow!(format!("Unknown SigHash byte {:#x}", sighash_byte[0])))?;
+
+    let preimage = self.sighash_preimage_impl(n_tx_in, sighash, subscript, value)?;
+    let message = Hash::sha_256d(&preimage).to_bytes();
+
+    let parsed_sig = match Signature::from_der_bytes_impl(der_sig) {
+      Ok(v) => v,
+      Err(_) => return Ok(false),
+    };
+
+    Ok(parsed_sig.verify_impl(&message, pub_key).unwrap_or(false))
+  }
+
+  /**
+   * Verifies a standard P2PKH input: pulls the `<sig> <pubkey>` pair out of the input's
+   * existing script_sig via `ScriptTemplate`, checks the signature against `prevout_script_pubkey`,
+   * and confirms the pubkey actually hashes to the HASH160 baked into that locking script.
+   */
+  pub(crate) fn verify_input_p2pkh_impl(&mut self, n_tx_in: usize, prevout_script_pubkey: &Script, value: u64) -> Result<bool> {
+    let input = self.get_input(n_tx_in).ok_or(anyhow!(format!("Could not get TxIn at index {}", n_tx_in)))?;
+    let script_sig = input.get_script_sig();
+
+    let script_sig_template = ScriptTemplate::from_asm_string_impl("OP_SIG OP_PUBKEY")?;
+    let script_sig_matches = script_sig.match_impl(&script_sig_template)?;
+
+    let signature = script_sig_matches.get(0).ok_or(anyhow!(format!("script_sig for input {} did not contain a signature", n_tx_in)))?;
+    let pub_key_match = script_sig_matches.get(1).ok_or(anyhow!(format!("script_sig for input {} did not contain a public key", n_tx_in)))?;
+
+    let pub_key = PublicKey::from_bytes_impl(pub_key_match.bytes())?;
+
+    let script_pubkey_template = ScriptTemplate::from_asm_string_impl("OP_DUP OP_HASH160 OP_PUBKEYHASH OP_EQUALVERIFY OP_CHECKSIG")?;
+    let script_pubkey_matches = prevout_script_pubkey.match_impl(&script_pubkey_template)?;
+    let expected_pub_key_hash = script_pubkey_matches.get(0).ok_or(anyhow!("prevout script_pubkey is not a standard P2PKH script"))?;
+
+    let pub_key_hash = Hash::hash_160(pub_key_match.bytes()).to_bytes();
+
+    if &pub_key_hash != expected_pub_key_hash.bytes() {
+      return Ok(false);
+    }
+
+    self.verify_signature_impl(n_tx_in, &pub_key, signature.bytes(), prevout_script_pubkey, value)
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Transaction {
+  pub fn verify_signature(&mut self, n_tx_in: usize, pub_key: &PublicKey, signature: &[u8], subscript: &Script, value: u64) -> Result<bool> {
+    Transaction::verify_signature_impl(self, n_tx_in, pub_key, signature, subscript, value)
+  }
+
+  pub fn verify_input_p2pkh(&mut self, n_tx_in: usize, prevout_script_pubkey: &Script, value: u64) -> Result<bool> {
+    Transaction::verify_input_p2pkh_impl(self, n_tx_in, prevout_script_pubkey, value)
+  }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl Transaction {
+  #[wasm_bindgen(js_name = verifySignature)]
+  pub fn verify_signature(&mut self, n_tx_in: usize, pub_key: &PublicKey, signature: &[u8], subscript: &Script, value: u64) -> Result<bool, JsValue> {
+    match Transaction::verify_signature_impl(self, n_tx_in, pub_key, signature, subscript, value) {
+      Ok(v) => Ok(v),
+      Err(e) => throw_str(&e.to_string()),
+    }
+  }
+
+  #[wasm_bindgen(js_name = verifyInputP2PKH)]
+  pub fn verify_input_p2pkh(&mut self, n_tx_in: usize, prevout_script_pubkey: &Script, value: u64) -> Result<bool, JsValue> {
+    match Transaction::verify_input_p2pkh_impl(self, n_tx_in, prevout_script_pubkey, value) {
+      Ok(v) => Ok(v),
+      Err(e) => throw_str(&e.to_string()),
+    }
+  }
+}